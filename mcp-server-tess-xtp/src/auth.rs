@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use extism_pdk::config;
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use serde::Deserialize;
+use sha2::Sha256;
+
+use crate::http_client;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Read,
+    Execute,
+}
+
+impl Scope {
+    fn parse(raw: &str) -> Option<Scope> {
+        match raw {
+            "read" => Some(Scope::Read),
+            "execute" => Some(Scope::Execute),
+            _ => None,
+        }
+    }
+}
+
+pub struct Principal {
+    pub subject: String,
+    pub scopes: Vec<Scope>,
+}
+
+impl Principal {
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
+
+fn extract_bearer_token(headers: &Option<HashMap<String, String>>) -> Option<String> {
+    let (_, value) = headers
+        .as_ref()?
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("authorization"))?;
+    value.strip_prefix("Bearer ").map(|token| token.trim().to_string())
+}
+
+#[derive(Deserialize)]
+struct LocalTokenClaims {
+    sub: String,
+    scopes: Vec<String>,
+    exp: i64,
+}
+
+/// Verifies a locally-issued `<base64url(payload)>.<base64url(hmac-sha256)>`
+/// token against the shared secret from plugin config, checking both the
+/// signature and the `exp` claim.
+fn verify_local_token(token: &str, secret: &str) -> Result<Principal> {
+    let (payload_b64, signature_b64) = token
+        .split_once('.')
+        .ok_or_else(|| anyhow!("token local malformado"))?;
+
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())?;
+    mac.update(payload_b64.as_bytes());
+    let signature = URL_SAFE_NO_PAD.decode(signature_b64)?;
+    mac.verify_slice(&signature)
+        .map_err(|_| anyhow!("assinatura do token inválida"))?;
+
+    let payload = URL_SAFE_NO_PAD.decode(payload_b64)?;
+    let claims: LocalTokenClaims = serde_json::from_slice(&payload)?;
+    if claims.exp < chrono::Utc::now().timestamp() {
+        return Err(anyhow!("token expirado"));
+    }
+
+    let scopes = claims.scopes.iter().filter_map(|s| Scope::parse(s)).collect();
+    Ok(Principal { subject: claims.sub, scopes })
+}
+
+#[derive(Deserialize)]
+struct IntrospectionResponse {
+    active: bool,
+    sub: Option<String>,
+    scope: Option<String>,
+}
+
+/// Validates the token against an RFC 7662-style introspection endpoint
+/// configured via `auth_introspection_url`.
+async fn introspect_token(client: &Client, endpoint: &str, token: &str) -> Result<Principal> {
+    let retry_cfg = http_client::retry_config_from_plugin();
+    let response = http_client::send_with_retry(&retry_cfg, || {
+        client.post(endpoint).form(&[("token", token)])
+    })
+    .await
+    .map_err(anyhow::Error::from)?;
+
+    let payload: IntrospectionResponse = response.error_for_status()?.json().await?;
+    if !payload.active {
+        return Err(anyhow!("token inativo"));
+    }
+
+    let scopes = payload
+        .scope
+        .unwrap_or_default()
+        .split_whitespace()
+        .filter_map(Scope::parse)
+        .collect();
+    Ok(Principal { subject: payload.sub.unwrap_or_default(), scopes })
+}
+
+/// Resolves the `Authorization: Bearer <token>` header into a `Principal`,
+/// using whichever token store `auth_mode` selects: `"introspection"` calls
+/// out to `auth_introspection_url`, anything else verifies a locally signed
+/// token against `auth_token_secret`.
+pub fn authenticate(client: &Client, rt: &tokio::runtime::Runtime, headers: &Option<HashMap<String, String>>) -> Result<Principal> {
+    let token = extract_bearer_token(headers).ok_or_else(|| anyhow!("cabeçalho Authorization ausente"))?;
+
+    match config::get("auth_mode")?.as_deref() {
+        Some("introspection") => {
+            let endpoint = config::get("auth_introspection_url")?
+                .ok_or_else(|| anyhow!("auth_introspection_url não configurado"))?;
+            rt.block_on(introspect_token(client, &endpoint, &token))
+        }
+        _ => {
+            let secret = config::get("auth_token_secret")?
+                .ok_or_else(|| anyhow!("auth_token_secret não configurado"))?;
+            verify_local_token(&token, &secret)
+        }
+    }
+}
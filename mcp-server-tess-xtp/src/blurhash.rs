@@ -0,0 +1,141 @@
+//! Minimal BlurHash encoder (https://blurha.sh) so `process_image` can emit a
+//! short placeholder string without depending on an external blurhash crate.
+
+const BASE83_CHARS: &[u8] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+fn encode_base83(mut value: u32, length: usize) -> String {
+    let mut chars = vec![0u8; length];
+    for slot in chars.iter_mut().rev() {
+        *slot = BASE83_CHARS[(value % 83) as usize];
+        value /= 83;
+    }
+    String::from_utf8(chars).expect("base83 alphabet is ASCII")
+}
+
+fn srgb_to_linear(channel: u8) -> f64 {
+    let c = channel as f64 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f64) -> u8 {
+    let v = value.clamp(0.0, 1.0);
+    let s = if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (s * 255.0 + 0.5) as u8
+}
+
+/// One DCT basis function evaluated over every pixel, weighted by linear
+/// light, and normalized by the image area.
+fn basis_component(i: u32, j: u32, width: u32, height: u32, rgba: &[u8]) -> (f64, f64, f64) {
+    let normalization = if i == 0 && j == 0 { 1.0 } else { 2.0 };
+    let (mut r, mut g, mut b) = (0.0, 0.0, 0.0);
+
+    for y in 0..height {
+        for x in 0..width {
+            let basis = normalization
+                * (std::f64::consts::PI * i as f64 * x as f64 / width as f64).cos()
+                * (std::f64::consts::PI * j as f64 * y as f64 / height as f64).cos();
+            let idx = ((y * width + x) * 4) as usize;
+            r += basis * srgb_to_linear(rgba[idx]);
+            g += basis * srgb_to_linear(rgba[idx + 1]);
+            b += basis * srgb_to_linear(rgba[idx + 2]);
+        }
+    }
+
+    let scale = 1.0 / (width as f64 * height as f64);
+    (r * scale, g * scale, b * scale)
+}
+
+fn encode_dc((r, g, b): (f64, f64, f64)) -> u32 {
+    let r = linear_to_srgb(r) as u32;
+    let g = linear_to_srgb(g) as u32;
+    let b = linear_to_srgb(b) as u32;
+    (r << 16) + (g << 8) + b
+}
+
+fn encode_ac((r, g, b): (f64, f64, f64), max_value: f64) -> u32 {
+    let quantize = |value: f64| -> u32 {
+        let normalized = value / max_value;
+        (normalized.signum() * normalized.abs().powf(0.5) * 9.0 + 9.5)
+            .floor()
+            .clamp(0.0, 18.0) as u32
+    };
+    quantize(r) * 19 * 19 + quantize(g) * 19 + quantize(b)
+}
+
+/// Encode an RGBA8 buffer into a BlurHash string using `components_x` by
+/// `components_y` DCT components (each in `1..=9`).
+pub fn encode(rgba: &[u8], width: u32, height: u32, components_x: u32, components_y: u32) -> String {
+    let components_x = components_x.clamp(1, 9);
+    let components_y = components_y.clamp(1, 9);
+
+    let mut factors = Vec::with_capacity((components_x * components_y) as usize);
+    for j in 0..components_y {
+        for i in 0..components_x {
+            factors.push(basis_component(i, j, width, height, rgba));
+        }
+    }
+
+    let dc = factors[0];
+    let ac = &factors[1..];
+
+    let mut hash = String::with_capacity(6 + ac.len() * 2);
+    let size_flag = (components_x - 1) + (components_y - 1) * 9;
+    hash.push_str(&encode_base83(size_flag, 1));
+
+    let max_value = if ac.is_empty() {
+        hash.push_str(&encode_base83(0, 1));
+        1.0
+    } else {
+        let actual_max = ac
+            .iter()
+            .flat_map(|(r, g, b)| [r.abs(), g.abs(), b.abs()])
+            .fold(0.0_f64, f64::max);
+        let quantized_max = ((actual_max * 166.0 - 0.5).floor() as i32).clamp(0, 82) as u32;
+        hash.push_str(&encode_base83(quantized_max, 1));
+        (quantized_max as f64 + 1.0) / 166.0
+    };
+
+    hash.push_str(&encode_base83(encode_dc(dc), 4));
+    for &factor in ac {
+        hash.push_str(&encode_base83(encode_ac(factor, max_value), 2));
+    }
+
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_ac_quantizes_with_the_canonical_rounding_offset() {
+        // floor(signPow(-0.5, 0.5) * 9 + 9.5) == 3 per the reference
+        // BlurHash formula; dropping the +0.5 offset produces 2 instead.
+        let packed = encode_ac((-0.5, 0.0, 0.0), 1.0);
+        assert_eq!(packed / (19 * 19), 3);
+    }
+
+    #[test]
+    fn encode_base83_matches_known_digits() {
+        assert_eq!(encode_base83(0, 1), "0");
+        assert_eq!(encode_base83(82, 1), "~");
+        assert_eq!(encode_base83(83, 2), "10");
+    }
+
+    #[test]
+    fn srgb_linear_round_trip_is_approximately_identity() {
+        for channel in [0u8, 64, 128, 200, 255] {
+            let roundtripped = linear_to_srgb(srgb_to_linear(channel));
+            assert!((roundtripped as i16 - channel as i16).abs() <= 1);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use anyhow::{anyhow, Result};
+use extism_pdk::config;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::http_client;
+
+#[derive(Serialize, Deserialize)]
+pub struct ChatCompletionRequest {
+    pub prompt: String,
+    pub history: Option<Vec<String>>,
+    #[serde(default)]
+    pub provider: ChatProviderKind,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct EmbeddingsRequest {
+    pub input: Vec<String>,
+    pub model: String,
+    #[serde(default)]
+    pub provider: ChatProviderKind,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatProviderKind {
+    #[default]
+    OpenAi,
+    Cohere,
+}
+
+struct ProviderConfig {
+    api_key: String,
+    base_url: String,
+    model: String,
+}
+
+/// Reads `{prefix}_api_key` / `{prefix}_base_url` / `{prefix}_model` from the
+/// Extism plugin config, falling back to the given defaults for the base URL
+/// and model (the API key has no sane default and must be configured).
+fn provider_config(prefix: &str, default_base_url: &str, default_model: &str) -> Result<ProviderConfig> {
+    let api_key = config::get(format!("{prefix}_api_key"))?
+        .ok_or_else(|| anyhow!("{prefix}_api_key não configurado"))?;
+    let base_url = config::get(format!("{prefix}_base_url"))?
+        .unwrap_or_else(|| default_base_url.to_string());
+    let model = config::get(format!("{prefix}_model"))?.unwrap_or_else(|| default_model.to_string());
+
+    Ok(ProviderConfig { api_key, base_url, model })
+}
+
+#[async_trait::async_trait]
+pub trait ChatProvider {
+    async fn chat(&self, client: &Client, prompt: &str, history: &[String]) -> Result<String>;
+    async fn embed(&self, client: &Client, input: &[String], model: &str) -> Result<Vec<Vec<f64>>>;
+}
+
+/// Builds the provider implementation selected by `ChatCompletionRequest::provider`
+/// / `EmbeddingsRequest::provider`, reading its credentials from plugin config.
+pub fn provider_for(kind: ChatProviderKind) -> Result<Box<dyn ChatProvider>> {
+    match kind {
+        ChatProviderKind::OpenAi => Ok(Box::new(OpenAiProvider::from_config()?)),
+        ChatProviderKind::Cohere => Ok(Box::new(CohereProvider::from_config()?)),
+    }
+}
+
+/// Alternates history entries as user/assistant turns, then appends the
+/// current prompt as the final user turn.
+fn history_to_messages(history: &[String], prompt: &str) -> Vec<serde_json::Value> {
+    let mut messages: Vec<serde_json::Value> = history
+        .iter()
+        .enumerate()
+        .map(|(i, content)| {
+            let role = if i % 2 == 0 { "user" } else { "assistant" };
+            json!({"role": role, "content": content})
+        })
+        .collect();
+    messages.push(json!({"role": "user", "content": prompt}));
+    messages
+}
+
+pub struct OpenAiProvider {
+    cfg: ProviderConfig,
+}
+
+impl OpenAiProvider {
+    pub fn from_config() -> Result<Self> {
+        Ok(Self {
+            cfg: provider_config("openai", "https://api.openai.com/v1", "gpt-4o-mini")?,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ChatProvider for OpenAiProvider {
+    async fn chat(&self, client: &Client, prompt: &str, history: &[String]) -> Result<String> {
+        let body = json!({
+            "model": self.cfg.model,
+            "messages": history_to_messages(history, prompt),
+        });
+
+        let retry_cfg = http_client::retry_config_from_plugin();
+        let response = http_client::send_with_retry(&retry_cfg, || {
+            client
+                .post(format!("{}/chat/completions", self.cfg.base_url))
+                .bearer_auth(&self.cfg.api_key)
+                .json(&body)
+        })
+        .await
+        .map_err(anyhow::Error::from)?;
+        let response = http_client::ensure_success(response).await.map_err(anyhow::Error::from)?;
+        let payload: serde_json::Value = response.json().await?;
+
+        payload["choices"][0]["message"]["content"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("resposta da OpenAI sem conteúdo"))
+    }
+
+    async fn embed(&self, client: &Client, input: &[String], model: &str) -> Result<Vec<Vec<f64>>> {
+        let body = json!({"model": model, "input": input});
+
+        let retry_cfg = http_client::retry_config_from_plugin();
+        let response = http_client::send_with_retry(&retry_cfg, || {
+            client
+                .post(format!("{}/embeddings", self.cfg.base_url))
+                .bearer_auth(&self.cfg.api_key)
+                .json(&body)
+        })
+        .await
+        .map_err(anyhow::Error::from)?;
+        let response = http_client::ensure_success(response).await.map_err(anyhow::Error::from)?;
+        let payload: serde_json::Value = response.json().await?;
+
+        parse_embeddings(&payload["data"], "embedding")
+    }
+}
+
+pub struct CohereProvider {
+    cfg: ProviderConfig,
+}
+
+impl CohereProvider {
+    pub fn from_config() -> Result<Self> {
+        Ok(Self {
+            cfg: provider_config("cohere", "https://api.cohere.com/v1", "command-r")?,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ChatProvider for CohereProvider {
+    async fn chat(&self, client: &Client, prompt: &str, history: &[String]) -> Result<String> {
+        let chat_history: Vec<serde_json::Value> = history
+            .iter()
+            .enumerate()
+            .map(|(i, content)| {
+                let role = if i % 2 == 0 { "USER" } else { "CHATBOT" };
+                json!({"role": role, "message": content})
+            })
+            .collect();
+
+        let body = json!({
+            "model": self.cfg.model,
+            "message": prompt,
+            "chat_history": chat_history,
+        });
+
+        let retry_cfg = http_client::retry_config_from_plugin();
+        let response = http_client::send_with_retry(&retry_cfg, || {
+            client
+                .post(format!("{}/chat", self.cfg.base_url))
+                .bearer_auth(&self.cfg.api_key)
+                .json(&body)
+        })
+        .await
+        .map_err(anyhow::Error::from)?;
+        let response = http_client::ensure_success(response).await.map_err(anyhow::Error::from)?;
+        let payload: serde_json::Value = response.json().await?;
+
+        payload["text"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("resposta da Cohere sem conteúdo"))
+    }
+
+    async fn embed(&self, client: &Client, input: &[String], model: &str) -> Result<Vec<Vec<f64>>> {
+        let body = json!({"texts": input, "model": model, "input_type": "search_document"});
+
+        let retry_cfg = http_client::retry_config_from_plugin();
+        let response = http_client::send_with_retry(&retry_cfg, || {
+            client
+                .post(format!("{}/embed", self.cfg.base_url))
+                .bearer_auth(&self.cfg.api_key)
+                .json(&body)
+        })
+        .await
+        .map_err(anyhow::Error::from)?;
+        let response = http_client::ensure_success(response).await.map_err(anyhow::Error::from)?;
+        let payload: serde_json::Value = response.json().await?;
+
+        parse_embeddings(&payload["embeddings"], "")
+    }
+}
+
+/// Pulls float vectors out of a JSON array, optionally indexing into a
+/// per-entry `field` first (OpenAI nests each vector under `"embedding"`;
+/// Cohere's `embeddings` array holds the vectors directly).
+fn parse_embeddings(value: &serde_json::Value, field: &str) -> Result<Vec<Vec<f64>>> {
+    let entries = value
+        .as_array()
+        .ok_or_else(|| anyhow!("resposta do provedor sem vetores de embedding"))?;
+
+    entries
+        .iter()
+        .map(|entry| {
+            let vector = if field.is_empty() { entry } else { &entry[field] };
+            vector
+                .as_array()
+                .ok_or_else(|| anyhow!("embedding inválido"))?
+                .iter()
+                .map(|v| v.as_f64().ok_or_else(|| anyhow!("valor de embedding inválido")))
+                .collect()
+        })
+        .collect()
+}
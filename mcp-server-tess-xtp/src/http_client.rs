@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use extism_pdk::config;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::Serialize;
+
+use crate::Response;
+
+#[derive(Clone, Copy)]
+pub struct TimeoutConfig {
+    pub connect_timeout: Duration,
+    pub request_timeout: Duration,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct RetryConfig {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+fn config_u64(key: &str, default: u64) -> u64 {
+    config::get(key)
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+pub fn timeout_config_from_plugin() -> TimeoutConfig {
+    TimeoutConfig {
+        connect_timeout: Duration::from_millis(config_u64("http_connect_timeout_ms", 5_000)),
+        request_timeout: Duration::from_millis(config_u64("http_request_timeout_ms", 30_000)),
+    }
+}
+
+pub fn retry_config_from_plugin() -> RetryConfig {
+    RetryConfig {
+        max_retries: config_u64("http_max_retries", 3) as u32,
+        base_delay: Duration::from_millis(config_u64("http_retry_base_delay_ms", 250)),
+        max_delay: Duration::from_millis(config_u64("http_retry_max_delay_ms", 5_000)),
+    }
+}
+
+/// Builds the `reqwest::Client` every outbound call should share, with
+/// connect/request timeouts pulled from plugin config.
+pub fn client_from_plugin_config() -> reqwest::Result<Client> {
+    let timeouts = timeout_config_from_plugin();
+    Client::builder()
+        .connect_timeout(timeouts.connect_timeout)
+        .timeout(timeouts.request_timeout)
+        .build()
+}
+
+/// Structured error returned once the retry budget is exhausted, so callers
+/// can surface it as a JSON `Response` body instead of an opaque `anyhow`
+/// error.
+#[derive(Debug, Clone, Serialize)]
+pub struct UpstreamError {
+    pub error: String,
+    pub attempts: u32,
+    pub status: Option<u16>,
+}
+
+impl std::fmt::Display for UpstreamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "falha upstream após {} tentativa(s): {}", self.attempts, self.error)
+    }
+}
+
+impl std::error::Error for UpstreamError {}
+
+impl UpstreamError {
+    /// A non-retryable 4xx reported by the upstream (bad API key, bad model,
+    /// missing object, ...) is passed through as-is so the caller sees the
+    /// real status; anything else (exhausted retries on 5xx/429, or a
+    /// transport error with no status at all) collapses to a generic 502.
+    pub fn into_response(self, headers: HashMap<String, String>) -> Response {
+        let status = match self.status {
+            Some(status @ 400..=499) if status != 429 => status,
+            _ => 502,
+        };
+        Response {
+            status,
+            body: serde_json::to_string(&self)
+                .unwrap_or_else(|_| r#"{"error":"falha ao serializar erro upstream"}"#.to_string()),
+            headers,
+        }
+    }
+}
+
+/// Looks up an `UpstreamError` hiding inside an `anyhow::Error` chain, for
+/// call sites that bubble errors through `?` before deciding how to respond.
+pub fn as_upstream_error(err: &anyhow::Error) -> Option<&UpstreamError> {
+    err.downcast_ref::<UpstreamError>()
+}
+
+/// Checks the status of a response already returned by `send_with_retry`
+/// (which only rejects on exhausted 429/5xx retries or transport errors, so a
+/// non-retryable 4xx like a bad API key or a missing object still reaches
+/// here as `Ok`). Converts anything outside 2xx into the same `UpstreamError`
+/// shape used for retry exhaustion, so every upstream failure — retried or
+/// not — ends up on the one `as_upstream_error` / `into_response` path.
+pub async fn ensure_success(response: reqwest::Response) -> Result<reqwest::Response, UpstreamError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let status_code = status.as_u16();
+    let body = response.text().await.unwrap_or_default();
+    Err(UpstreamError {
+        error: if body.is_empty() { status.to_string() } else { body },
+        attempts: 1,
+        status: Some(status_code),
+    })
+}
+
+fn retry_after_seconds(response: &reqwest::Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+/// Pseudo-random jitter source: no system RNG is available inside the Wasm
+/// plugin, so a xorshift seeded by the current timestamp stands in for one.
+fn jitter_millis(attempt: u32, bound_ms: u64) -> u64 {
+    if bound_ms == 0 {
+        return 0;
+    }
+    let seed = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0) as u64 ^ (attempt as u64).wrapping_mul(0x9E37);
+    let mut x = seed ^ 0x9E3779B97F4A7C15;
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    x % bound_ms
+}
+
+fn backoff_delay(config: &RetryConfig, attempt: u32, retry_after: Option<Duration>) -> Duration {
+    if let Some(delay) = retry_after {
+        return delay.min(config.max_delay);
+    }
+    let exponential = config.base_delay.saturating_mul(1u32.wrapping_shl(attempt).max(1));
+    let capped = exponential.min(config.max_delay);
+    Duration::from_millis(jitter_millis(attempt, capped.as_millis() as u64))
+}
+
+/// Sends a request built fresh by `build_request` on every attempt, retrying
+/// up to `config.max_retries` times with exponential backoff plus jitter on
+/// 429/5xx responses and transport errors, honoring `Retry-After` when the
+/// upstream sends one.
+pub async fn send_with_retry<F>(config: &RetryConfig, build_request: F) -> Result<reqwest::Response, UpstreamError>
+where
+    F: Fn() -> RequestBuilder,
+{
+    let mut last_status = None;
+    let mut last_error = None;
+
+    for attempt in 0..=config.max_retries {
+        match build_request().send().await {
+            Ok(response) => {
+                let status = response.status();
+                if status.is_success() || (!status.is_server_error() && status != StatusCode::TOO_MANY_REQUESTS) {
+                    return Ok(response);
+                }
+                last_status = Some(status.as_u16());
+                if attempt == config.max_retries {
+                    break;
+                }
+                let delay = backoff_delay(config, attempt, retry_after_seconds(&response));
+                tokio::time::sleep(delay).await;
+            }
+            Err(err) => {
+                last_error = Some(err.to_string());
+                if attempt == config.max_retries {
+                    break;
+                }
+                tokio::time::sleep(backoff_delay(config, attempt, None)).await;
+            }
+        }
+    }
+
+    Err(UpstreamError {
+        error: last_error.unwrap_or_else(|| "status de erro recebido do upstream".to_string()),
+        attempts: config.max_retries + 1,
+        status: last_status,
+    })
+}
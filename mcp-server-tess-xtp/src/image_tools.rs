@@ -0,0 +1,127 @@
+use anyhow::Result;
+use exif::{In, Tag};
+use image::GenericImageView;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::blurhash;
+use crate::http_client;
+use crate::media;
+
+#[derive(Serialize, Deserialize)]
+pub struct ImageProcessingResult {
+    pub width: u32,
+    pub height: u32,
+    pub format: String,
+    pub has_faces: bool,
+    pub description: String,
+    pub tags: Vec<String>,
+    pub blurhash: String,
+    pub stored_url: Option<String>,
+}
+
+/// BlurHash is computed on a downscaled copy so the DCT pass (O(width *
+/// height * components)) stays cheap regardless of the source resolution.
+const BLURHASH_MAX_DIMENSION: u32 = 32;
+const BLURHASH_COMPONENTS_X: u32 = 4;
+const BLURHASH_COMPONENTS_Y: u32 = 3;
+
+fn guess_format_name(bytes: &[u8]) -> String {
+    image::guess_format(bytes)
+        .map(|fmt| format!("{:?}", fmt).to_lowercase())
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Reads orientation, camera make/model, and GPS coordinates out of the
+/// image's EXIF block, when present. Missing or unparsable EXIF data simply
+/// yields no tags rather than an error, since most web images lack it.
+fn exif_tags(bytes: &[u8]) -> Vec<String> {
+    let mut tags = Vec::new();
+
+    let exif = match exif::Reader::new().read_from_container(&mut std::io::Cursor::new(bytes)) {
+        Ok(exif) => exif,
+        Err(_) => return tags,
+    };
+
+    if let Some(field) = exif.get_field(Tag::Orientation, In::PRIMARY) {
+        tags.push(format!("orientation:{}", field.display_value()));
+    }
+
+    let make = exif
+        .get_field(Tag::Make, In::PRIMARY)
+        .map(|f| f.display_value().to_string());
+    let model = exif
+        .get_field(Tag::Model, In::PRIMARY)
+        .map(|f| f.display_value().to_string());
+    if make.is_some() || model.is_some() {
+        let camera = [make, model]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ");
+        tags.push(format!("camera:{}", camera.trim()));
+    }
+
+    let lat = exif.get_field(Tag::GPSLatitude, In::PRIMARY);
+    let lon = exif.get_field(Tag::GPSLongitude, In::PRIMARY);
+    if let (Some(lat), Some(lon)) = (lat, lon) {
+        tags.push(format!("gps:{},{}", lat.display_value(), lon.display_value()));
+    }
+
+    tags
+}
+
+/// Fetches the image bytes at `url`, decodes them, and fills in the
+/// dimensions, format, EXIF tags, a BlurHash placeholder, and `stored_url`
+/// when object storage is configured. No face detection is run: `has_faces`
+/// is always `false`, and `description` is a fixed sentence naming the URL.
+pub async fn process_image(client: &Client, url: &str) -> Result<ImageProcessingResult> {
+    let retry_cfg = http_client::retry_config_from_plugin();
+    let response = http_client::send_with_retry(&retry_cfg, || client.get(url))
+        .await
+        .map_err(anyhow::Error::from)?;
+    let response = http_client::ensure_success(response).await.map_err(anyhow::Error::from)?;
+    let bytes = response.bytes().await?;
+
+    let format = guess_format_name(&bytes);
+    let image = image::load_from_memory(&bytes)?;
+    let (width, height) = image.dimensions();
+
+    let mut blurhash_source = image.clone();
+    if width > BLURHASH_MAX_DIMENSION || height > BLURHASH_MAX_DIMENSION {
+        blurhash_source = image.thumbnail(BLURHASH_MAX_DIMENSION, BLURHASH_MAX_DIMENSION);
+    }
+    let rgba = blurhash_source.to_rgba8();
+    let (bh_width, bh_height) = rgba.dimensions();
+    let blurhash = blurhash::encode(
+        rgba.as_raw(),
+        bh_width,
+        bh_height,
+        BLURHASH_COMPONENTS_X,
+        BLURHASH_COMPONENTS_Y,
+    );
+
+    let tags = exif_tags(&bytes);
+
+    // Persiste no object storage S3-compatível quando configurado; content-addressed
+    // pelo hash dos bytes, para que reprocessar a mesma imagem gere a mesma stored_url
+    let stored_url = match media::S3Config::from_plugin_config()? {
+        Some(cfg) => {
+            let id = media::hex_sha256(&bytes);
+            let content_type = format!("image/{format}");
+            Some(media::store_object(client, &cfg, &id, &bytes, &content_type).await?)
+        }
+        None => None,
+    };
+
+    Ok(ImageProcessingResult {
+        width,
+        height,
+        format,
+        has_faces: false,
+        description: format!("Imagem em {} processada via backend Rust", url),
+        tags,
+        blurhash,
+        stored_url,
+    })
+}
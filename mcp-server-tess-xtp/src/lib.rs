@@ -1,8 +1,16 @@
 use extism_pdk::*;
 use serde::{Deserialize, Serialize};
-use reqwest::Client;
 use std::collections::HashMap;
 use anyhow::{Result, anyhow};
+use base64::Engine;
+
+mod auth;
+mod blurhash;
+mod chat;
+mod http_client;
+mod image_tools;
+mod media;
+mod metrics;
 
 #[derive(Deserialize)]
 struct Request {
@@ -38,38 +46,96 @@ struct MCPExecuteRequest {
     params: Option<serde_json::Value>
 }
 
-#[derive(Serialize, Deserialize)]
-struct ImageProcessingResult {
-    width: u32,
-    height: u32,
-    format: String,
-    has_faces: bool,
-    description: String,
-    tags: Vec<String>,
-}
-
-#[derive(Serialize, Deserialize)]
-struct ChatCompletionRequest {
-    prompt: String,
-    history: Option<Vec<String>>,
-}
-
 #[plugin_fn]
 pub fn handle_request(request: Json<Request>) -> FnResult<Json<Response>> {
     let req = request.into_inner();
-    
+
     // Configurar headers padrão
     let mut headers = HashMap::new();
     headers.insert("Content-Type".to_string(), "application/json".to_string());
-    
+
+    // /health e /metrics ficam públicos (scrapers do Prometheus não carregam token);
+    // todo o resto exige um Bearer token válido
+    if !(req.method == "GET" && (req.path == "/health" || req.path == "/metrics")) {
+        let client = http_client::client_from_plugin_config()?;
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        let principal = match auth::authenticate(&client, &rt, &req.headers) {
+            Ok(principal) => principal,
+            Err(_) => {
+                let _ = metrics::record_request(&req.method, &req.path, 401);
+                return Ok(Json(Response {
+                    status: 401,
+                    body: r#"{"error":"token ausente ou inválido"}"#.to_string(),
+                    headers
+                }));
+            }
+        };
+        headers.insert("X-Authenticated-Subject".to_string(), principal.subject.clone());
+
+        let required_scope = match (req.method.as_str(), req.path.as_str()) {
+            ("GET", "/api/mcp/tools") => Some(auth::Scope::Read),
+            ("POST", "/api/mcp/execute") => Some(auth::Scope::Execute),
+            ("GET", path) if path.starts_with("/api/media/") => Some(auth::Scope::Read),
+            _ => None
+        };
+
+        if let Some(scope) = required_scope {
+            if !principal.has_scope(scope) {
+                let _ = metrics::record_request(&req.method, &req.path, 403);
+                return Ok(Json(Response {
+                    status: 403,
+                    body: r#"{"error":"escopo insuficiente para esta operação"}"#.to_string(),
+                    headers
+                }));
+            }
+        }
+    }
+
+    if req.method == "GET" && req.path == "/metrics" {
+        let mut metrics_headers = HashMap::new();
+        metrics_headers.insert("Content-Type".to_string(), "text/plain; version=0.0.4; charset=utf-8".to_string());
+        return Ok(Json(Response {
+            status: 200,
+            body: metrics::render()?,
+            headers: metrics_headers
+        }));
+    }
+
+    // Rótulo de ferramenta para métricas por-tool, sem afetar o tratamento de erro de parsing em `route`
+    let tool_label = if req.method == "POST" && req.path == "/api/mcp/execute" {
+        serde_json::from_str::<MCPExecuteRequest>(&req.body).ok().map(|e| e.tool)
+    } else {
+        None
+    };
+
+    let started_at = chrono::Utc::now();
+    let outcome = route(&req, headers);
+    let elapsed_ms = (chrono::Utc::now() - started_at).num_milliseconds() as f64;
+
+    let status = match &outcome {
+        Ok(response) => response.status,
+        Err(_) => 500
+    };
+    let _ = metrics::record_request(&req.method, &req.path, status);
+    if let Some(tool) = tool_label {
+        let _ = metrics::record_tool_execution(&tool, status, elapsed_ms);
+    }
+
+    Ok(Json(outcome?))
+}
+
+fn route(req: &Request, headers: HashMap<String, String>) -> Result<Response> {
     match (req.method.as_str(), req.path.as_str()) {
         // Health check
         ("GET", "/health") => {
-            Ok(Json(Response {
+            Ok(Response {
                 status: 200,
                 body: r#"{"status":"ok","message":"TESS proxy server is running"}"#.to_string(),
                 headers
-            }))
+            })
         },
         
         // Listar ferramentas MCP
@@ -77,158 +143,205 @@ pub fn handle_request(request: Json<Request>) -> FnResult<Json<Response>> {
             let session_id = req.query.as_ref()
                 .and_then(|q| q.get("session_id"))
                 .ok_or_else(|| anyhow!("session_id não fornecido"))?;
-                
+
             // Se houver um parâmetro resource, processa como solicitação de recurso
             if let Some(resource) = req.query.as_ref().and_then(|q| q.get("resource")) {
                 if resource.starts_with("chat_history://") {
                     let chat_id = resource.strip_prefix("chat_history://").unwrap_or("unknown");
-                    return Ok(Json(Response {
+                    return Ok(Response {
                         status: 200,
-                        body: format!("Histórico de chat {} (via Rust): Recuperado em {}", 
+                        body: format!("Histórico de chat {} (via Rust): Recuperado em {}",
                                       chat_id, chrono::Utc::now().to_rfc3339()),
                         headers
-                    }));
+                    });
                 }
-                
+
                 // Recurso não suportado
-                return Ok(Json(Response {
+                return Ok(Response {
                     status: 404,
                     body: format!("{{\"error\":\"Recurso não encontrado\",\"resource\":\"{}\"}}", resource),
                     headers
-                }));
+                });
             }
-                
-            // Fazer requisição para o MCP.run
-            let client = Client::new();
+
+            // Fazer requisição para o MCP.run, com timeout e retry com backoff
+            let client = http_client::client_from_plugin_config()?;
+            let retry_cfg = http_client::retry_config_from_plugin();
             let rt = tokio::runtime::Builder::new_current_thread()
                 .enable_all()
                 .build()?;
-                
-            let response = rt.block_on(async {
+
+            let response = match rt.block_on(http_client::send_with_retry(&retry_cfg, || {
                 client.get("https://www.mcp.run/api/mcp/get-tools")
                     .query(&[("session_id", session_id)])
-                    .send()
-                    .await
-            })?;
-            
+            })) {
+                Ok(response) => response,
+                Err(err) => {
+                    let _ = metrics::record_upstream_error("mcp_run");
+                    return Ok(err.into_response(headers));
+                }
+            };
+
             let status = response.status();
-            
+
             if !status.is_success() {
                 let error_text = rt.block_on(async {
                     response.text().await
                 })?;
-                
-                return Ok(Json(Response {
+
+                return Ok(Response {
                     status: status.as_u16(),
                     body: error_text,
                     headers
-                }));
+                });
             }
-            
+
             // Processar e retornar ferramentas
             let tools: Vec<MCPTool> = rt.block_on(async {
                 response.json().await
             })?;
-            
+
             let response_body = serde_json::to_string(&MCPToolsResponse { tools })?;
-            
-            Ok(Json(Response {
+
+            Ok(Response {
                 status: 200,
                 body: response_body,
                 headers
-            }))
+            })
         },
-        
+
         // Executar ferramenta MCP
         ("POST", "/api/mcp/execute") => {
             let session_id = req.query.as_ref()
                 .and_then(|q| q.get("session_id"))
                 .ok_or_else(|| anyhow!("session_id não fornecido"))?;
-                
+
             // Parsear corpo da requisição
             let execute_req: MCPExecuteRequest = serde_json::from_str(&req.body)?;
-            
+
             // Processar ferramentas locais
             match execute_req.tool.as_str() {
                 "health_check" => {
                     // Health check simples
-                    return Ok(Json(Response {
+                    return Ok(Response {
                         status: 200,
                         body: r#"{"status":"ok","message":"Rust backend is healthy"}"#.to_string(),
                         headers
-                    }));
+                    });
                 },
                 "search_info" => {
                     // Implementação de pesquisa em Rust
                     if let Some(params) = &execute_req.params {
                         if let Some(query) = params.get("query").and_then(|q| q.as_str()) {
                             let result = format!(
-                                "Resultados para '{}' (via Rust): Encontrados 3 documentos relevantes em {}.", 
+                                "Resultados para '{}' (via Rust): Encontrados 3 documentos relevantes em {}.",
                                 query, chrono::Utc::now().to_rfc3339()
                             );
-                            return Ok(Json(Response {
+                            return Ok(Response {
                                 status: 200,
                                 body: result,
                                 headers
-                            }));
+                            });
                         }
                     }
-                    return Ok(Json(Response {
+                    return Ok(Response {
                         status: 400,
                         body: r#"{"error":"Parâmetro 'query' não fornecido"}"#.to_string(),
                         headers
-                    }));
+                    });
                 },
                 "process_image" => {
-                    // Processamento de imagem (simulado)
+                    // Processamento real de imagem: baixa os bytes, decodifica
+                    // com a crate `image`, extrai EXIF e gera um BlurHash
                     if let Some(params) = &execute_req.params {
                         if let Some(url) = params.get("url").and_then(|u| u.as_str()) {
-                            // Simulação de processamento de imagem
-                            let result = ImageProcessingResult {
-                                width: 800,
-                                height: 600,
-                                format: "jpeg".to_string(),
-                                has_faces: true,
-                                description: format!("Imagem em {} processada via backend Rust", url),
-                                tags: vec!["imagem".to_string(), "processada".to_string(), "rust".to_string()],
+                            let client = http_client::client_from_plugin_config()?;
+                            let rt = tokio::runtime::Builder::new_current_thread()
+                                .enable_all()
+                                .build()?;
+
+                            return match rt.block_on(image_tools::process_image(&client, url)) {
+                                Ok(result) => Ok(Response {
+                                    status: 200,
+                                    body: serde_json::to_string(&result)?,
+                                    headers
+                                }),
+                                Err(err) => match http_client::as_upstream_error(&err) {
+                                    Some(upstream) => {
+                                        let _ = metrics::record_upstream_error("image_source");
+                                        Ok(upstream.clone().into_response(headers))
+                                    }
+                                    None => Err(err),
+                                },
                             };
-                            
-                            return Ok(Json(Response {
-                                status: 200,
-                                body: serde_json::to_string(&result)?,
-                                headers
-                            }));
                         }
                     }
-                    return Ok(Json(Response {
+                    return Ok(Response {
                         status: 400,
                         body: r#"{"error":"Parâmetro 'url' não fornecido"}"#.to_string(),
                         headers
-                    }));
+                    });
                 },
                 "chat_completion" => {
-                    // Processamento de chat completion
+                    // Encaminha para o provedor de LLM configurado (OpenAI-compatible ou Cohere)
                     if let Some(params) = &execute_req.params {
-                        let chat_req: ChatCompletionRequest = serde_json::from_value(params.clone())?;
-                        
-                        // Simulação de resposta do chat
-                        let response = format!(
-                            "Resposta Rust para: {}... (processada em {})",
-                            &chat_req.prompt[..std::cmp::min(50, chat_req.prompt.len())],
-                            chrono::Utc::now().to_rfc3339()
-                        );
-                        
-                        return Ok(Json(Response {
-                            status: 200,
-                            body: response,
-                            headers
-                        }));
+                        let chat_req: chat::ChatCompletionRequest = serde_json::from_value(params.clone())?;
+                        let provider = chat::provider_for(chat_req.provider)?;
+                        let history = chat_req.history.unwrap_or_default();
+
+                        let client = http_client::client_from_plugin_config()?;
+                        let rt = tokio::runtime::Builder::new_current_thread()
+                            .enable_all()
+                            .build()?;
+
+                        return match rt.block_on(provider.chat(&client, &chat_req.prompt, &history)) {
+                            Ok(text) => Ok(Response { status: 200, body: text, headers }),
+                            Err(err) => match http_client::as_upstream_error(&err) {
+                                Some(upstream) => {
+                                    let _ = metrics::record_upstream_error("llm_provider");
+                                    Ok(upstream.clone().into_response(headers))
+                                }
+                                None => Err(err),
+                            },
+                        };
                     }
-                    return Ok(Json(Response {
+                    return Ok(Response {
                         status: 400,
                         body: r#"{"error":"Parâmetros inválidos para chat completion"}"#.to_string(),
                         headers
-                    }));
+                    });
+                },
+                "embeddings" => {
+                    // Vetores de embedding via provedor configurado, para casos de uso de RAG/busca
+                    if let Some(params) = &execute_req.params {
+                        let embed_req: chat::EmbeddingsRequest = serde_json::from_value(params.clone())?;
+                        let provider = chat::provider_for(embed_req.provider)?;
+
+                        let client = http_client::client_from_plugin_config()?;
+                        let rt = tokio::runtime::Builder::new_current_thread()
+                            .enable_all()
+                            .build()?;
+
+                        return match rt.block_on(provider.embed(&client, &embed_req.input, &embed_req.model)) {
+                            Ok(vectors) => Ok(Response {
+                                status: 200,
+                                body: serde_json::to_string(&vectors)?,
+                                headers
+                            }),
+                            Err(err) => match http_client::as_upstream_error(&err) {
+                                Some(upstream) => {
+                                    let _ = metrics::record_upstream_error("llm_provider");
+                                    Ok(upstream.clone().into_response(headers))
+                                }
+                                None => Err(err),
+                            },
+                        };
+                    }
+                    return Ok(Response {
+                        status: 400,
+                        body: r#"{"error":"Parâmetros inválidos para embeddings"}"#.to_string(),
+                        headers
+                    });
                 },
                 _ => {
                     // Se a ferramenta não for local, encaminha para o MCP.run
@@ -236,43 +349,103 @@ pub fn handle_request(request: Json<Request>) -> FnResult<Json<Response>> {
                     let mut mcp_data = HashMap::new();
                     mcp_data.insert("session_id", session_id.to_string());
                     mcp_data.insert("tool", execute_req.tool);
-                    
+
                     if let Some(params) = execute_req.params {
                         mcp_data.insert("params", params.to_string());
                     }
-                    
-                    // Fazer requisição para o MCP.run
-                    let client = Client::new();
+
+                    // Fazer requisição para o MCP.run, com timeout e retry com backoff
+                    let client = http_client::client_from_plugin_config()?;
+                    let retry_cfg = http_client::retry_config_from_plugin();
                     let rt = tokio::runtime::Builder::new_current_thread()
                         .enable_all()
                         .build()?;
-                        
-                    let response = rt.block_on(async {
-                        client.post("https://www.mcp.run/api/mcp/tool-call")
-                            .json(&mcp_data)
-                            .send()
-                            .await
-                    })?;
-                    
+
+                    let response = match rt.block_on(http_client::send_with_retry(&retry_cfg, || {
+                        client.post("https://www.mcp.run/api/mcp/tool-call").json(&mcp_data)
+                    })) {
+                        Ok(response) => response,
+                        Err(err) => {
+                            let _ = metrics::record_upstream_error("mcp_run");
+                            return Ok(err.into_response(headers));
+                        }
+                    };
+
                     let status = response.status();
                     let response_text = rt.block_on(async {
                         response.text().await
                     })?;
-                    
-                    Ok(Json(Response {
+
+                    Ok(Response {
                         status: status.as_u16(),
                         body: response_text,
                         headers
-                    }))
+                    })
                 }
             }
         },
-        
+
+        // Servir mídia processada do object storage, com suporte a Range
+        (method, path) if method == "GET" && path.starts_with("/api/media/") => {
+            let id = path.trim_start_matches("/api/media/");
+            if !media::is_valid_object_id(id) {
+                return Ok(Response {
+                    status: 400,
+                    body: r#"{"error":"id do objeto inválido"}"#.to_string(),
+                    headers
+                });
+            }
+
+            let cfg = match media::S3Config::from_plugin_config()? {
+                Some(cfg) => cfg,
+                None => return Ok(Response {
+                    status: 404,
+                    body: r#"{"error":"armazenamento de mídia não configurado"}"#.to_string(),
+                    headers
+                })
+            };
+
+            let client = http_client::client_from_plugin_config()?;
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?;
+
+            // Encaminha o Range recebido direto para o GET no S3, em vez de
+            // baixar o objeto inteiro e fatiar em memória aqui
+            let range_header = req.headers.as_ref()
+                .and_then(|h| h.iter().find(|(k, _)| k.eq_ignore_ascii_case("range")))
+                .map(|(_, v)| v.as_str());
+
+            let object = match rt.block_on(media::fetch_object(&client, &cfg, id, range_header)) {
+                Ok(object) => object,
+                Err(err) => match http_client::as_upstream_error(&err) {
+                    Some(upstream) => {
+                        let _ = metrics::record_upstream_error("media_storage");
+                        return Ok(upstream.clone().into_response(headers));
+                    }
+                    None => return Err(err)
+                }
+            };
+
+            let mut media_headers = headers;
+            media_headers.insert("Content-Type".to_string(), object.content_type);
+            media_headers.insert("Accept-Ranges".to_string(), "bytes".to_string());
+            if let Some(content_range) = object.content_range {
+                media_headers.insert("Content-Range".to_string(), content_range);
+            }
+
+            Ok(Response {
+                status: object.status,
+                body: base64::engine::general_purpose::STANDARD.encode(&object.bytes),
+                headers: media_headers
+            })
+        },
+
         // Rota não encontrada
-        _ => Ok(Json(Response {
+        _ => Ok(Response {
             status: 404,
             body: r#"{"error":"Endpoint não encontrado"}"#.to_string(),
             headers
-        }))
+        })
     }
-} 
\ No newline at end of file
+}
\ No newline at end of file
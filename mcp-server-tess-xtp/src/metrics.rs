@@ -0,0 +1,196 @@
+//! Prometheus text-format metrics for the `/metrics` endpoint.
+//!
+//! The plugin has no long-lived process: each invocation gets a fresh Wasm
+//! instance, so counters are persisted through Extism's host-provided
+//! key/value vars (`extism_pdk::var`) and read-modify-written on every call.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::Result;
+use extism_pdk::var;
+use serde::{Deserialize, Serialize};
+
+const REGISTRY_VAR: &str = "metrics_registry";
+const LATENCY_BUCKETS_MS: &[f64] = &[5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0];
+
+#[derive(Default, Serialize, Deserialize)]
+struct Registry {
+    counters: HashMap<String, f64>,
+    histogram_bucket_counts: HashMap<String, Vec<u64>>,
+    histogram_sum: HashMap<String, f64>,
+    histogram_count: HashMap<String, u64>,
+}
+
+fn load() -> Result<Registry> {
+    match var::get::<Vec<u8>>(REGISTRY_VAR)? {
+        Some(bytes) => Ok(serde_json::from_slice(&bytes).unwrap_or_default()),
+        None => Ok(Registry::default()),
+    }
+}
+
+fn store(registry: &Registry) -> Result<()> {
+    var::set(REGISTRY_VAR, serde_json::to_vec(registry)?)?;
+    Ok(())
+}
+
+fn series_key(name: &str, labels: &[(&str, &str)]) -> String {
+    if labels.is_empty() {
+        return name.to_string();
+    }
+    let label_str = labels
+        .iter()
+        .map(|(k, v)| format!("{k}=\"{v}\""))
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("{name}{{{label_str}}}")
+}
+
+fn incr_counter(registry: &mut Registry, name: &str, labels: &[(&str, &str)]) {
+    *registry.counters.entry(series_key(name, labels)).or_insert(0.0) += 1.0;
+}
+
+fn observe_histogram(registry: &mut Registry, name: &str, labels: &[(&str, &str)], value_seconds: f64) {
+    let key = series_key(name, labels);
+    let bucket_idx = LATENCY_BUCKETS_MS
+        .iter()
+        .position(|bound_ms| value_seconds * 1000.0 <= *bound_ms)
+        .unwrap_or(LATENCY_BUCKETS_MS.len());
+
+    let counts = registry
+        .histogram_bucket_counts
+        .entry(key.clone())
+        .or_insert_with(|| vec![0u64; LATENCY_BUCKETS_MS.len() + 1]);
+    counts[bucket_idx] += 1;
+
+    *registry.histogram_sum.entry(key.clone()).or_insert(0.0) += value_seconds;
+    *registry.histogram_count.entry(key).or_insert(0) += 1;
+}
+
+/// Collapses a request path to its route template before it's used as a
+/// metric label. The raw path can carry caller-controlled segments (a media
+/// object id, a typo'd route) that would otherwise mint a brand-new
+/// permanent Prometheus series per distinct value, and the whole registry is
+/// round-tripped through JSON on every request.
+fn normalize_path(path: &str) -> &str {
+    match path {
+        "/health" | "/metrics" | "/api/mcp/tools" | "/api/mcp/execute" => path,
+        path if path.starts_with("/api/media/") => "/api/media/:id",
+        _ => "/other",
+    }
+}
+
+/// Records one request, labeled by method/path/status.
+pub fn record_request(method: &str, path: &str, status: u16) -> Result<()> {
+    let mut registry = load()?;
+    let path = normalize_path(path);
+    let status = status.to_string();
+    incr_counter(
+        &mut registry,
+        "tess_requests_total",
+        &[("method", method), ("path", path), ("status", &status)],
+    );
+    store(&registry)
+}
+
+/// Collapses a tool name to a known label before it's used as a metric
+/// label. `tool` comes straight off the caller-supplied request body, so
+/// without this any client could mint a brand-new permanent series just by
+/// sending a novel `tool` value — the same unbounded-growth problem
+/// `normalize_path` fixes for `path`.
+fn normalize_tool(tool: &str) -> &str {
+    match tool {
+        "health_check" | "search_info" | "process_image" | "chat_completion" | "embeddings" => tool,
+        _ => "forwarded",
+    }
+}
+
+/// Records one tool execution (local or forwarded to MCP.run), with its
+/// outcome status and latency.
+pub fn record_tool_execution(tool: &str, status: u16, duration_ms: f64) -> Result<()> {
+    let mut registry = load()?;
+    let tool = normalize_tool(tool);
+    let status = status.to_string();
+    incr_counter(
+        &mut registry,
+        "tess_tool_executions_total",
+        &[("tool", tool), ("status", &status)],
+    );
+    observe_histogram(
+        &mut registry,
+        "tess_tool_execution_duration_seconds",
+        &[("tool", tool)],
+        duration_ms / 1000.0,
+    );
+    store(&registry)
+}
+
+/// Records one failed upstream call (MCP.run, an LLM provider, the image
+/// source or the media store), labeled by upstream.
+pub fn record_upstream_error(upstream: &str) -> Result<()> {
+    let mut registry = load()?;
+    incr_counter(&mut registry, "tess_upstream_errors_total", &[("upstream", upstream)]);
+    store(&registry)
+}
+
+fn base_name(key: &str) -> &str {
+    key.split('{').next().unwrap_or(key)
+}
+
+fn with_le_label(key: &str, base: &str, le: f64) -> String {
+    let le_str = if le.is_infinite() { "+Inf".to_string() } else { le.to_string() };
+    let existing_labels = key.strip_prefix(base).unwrap_or("");
+    match existing_labels.strip_suffix('}') {
+        Some(inner) => format!("{inner},le=\"{le_str}\"}}"),
+        None => format!("{{le=\"{le_str}\"}}"),
+    }
+}
+
+/// Renders the registry as Prometheus text-format exposition, with
+/// `# HELP`/`# TYPE` headers grouped by metric name.
+pub fn render() -> Result<String> {
+    let registry = load()?;
+    let mut out = String::new();
+
+    let counters_by_base: BTreeMap<&str, Vec<(&String, &f64)>> =
+        registry.counters.iter().fold(BTreeMap::new(), |mut acc, (k, v)| {
+            acc.entry(base_name(k)).or_default().push((k, v));
+            acc
+        });
+    for (base, mut entries) in counters_by_base {
+        entries.sort_by_key(|(k, _)| k.as_str());
+        out.push_str(&format!("# HELP {base} Total count of {base}.\n"));
+        out.push_str(&format!("# TYPE {base} counter\n"));
+        for (key, value) in entries {
+            out.push_str(&format!("{key} {value}\n"));
+        }
+    }
+
+    let hist_keys_by_base: BTreeMap<&str, Vec<&String>> =
+        registry.histogram_count.keys().fold(BTreeMap::new(), |mut acc, k| {
+            acc.entry(base_name(k)).or_default().push(k);
+            acc
+        });
+    for (base, mut keys) in hist_keys_by_base {
+        keys.sort();
+        out.push_str(&format!("# HELP {base} Latency histogram for {base}, in seconds.\n"));
+        out.push_str(&format!("# TYPE {base} histogram\n"));
+        for key in keys {
+            let counts = &registry.histogram_bucket_counts[key];
+            let mut cumulative = 0u64;
+            for (i, bound_ms) in LATENCY_BUCKETS_MS.iter().enumerate() {
+                cumulative += counts[i];
+                let le_label = with_le_label(key, base, bound_ms / 1000.0);
+                out.push_str(&format!("{base}_bucket{le_label} {cumulative}\n"));
+            }
+            cumulative += counts[LATENCY_BUCKETS_MS.len()];
+            let le_inf_label = with_le_label(key, base, f64::INFINITY);
+            out.push_str(&format!("{base}_bucket{le_inf_label} {cumulative}\n"));
+
+            let label_suffix = key.strip_prefix(base).unwrap_or("");
+            out.push_str(&format!("{base}_sum{label_suffix} {}\n", registry.histogram_sum[key]));
+            out.push_str(&format!("{base}_count{label_suffix} {}\n", registry.histogram_count[key]));
+        }
+    }
+
+    Ok(out)
+}